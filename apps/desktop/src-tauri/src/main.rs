@@ -64,6 +64,12 @@ fn get_cursor_position(window: tauri::Window) -> CursorPosition {
     }
 }
 
+/// 获取当前操作系统标识（macos / windows / linux 等），供前端按平台筛选插件
+#[tauri::command]
+fn get_platform() -> &'static str {
+    std::env::consts::OS
+}
+
 /// 获取文件的 MIME 类型
 fn get_mime_type(path: &str) -> &'static str {
     let extension = Path::new(path)
@@ -129,7 +135,7 @@ fn handle_localfile_protocol(request: &Request) -> Result<Response, Box<dyn std:
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![get_cursor_position])
+        .invoke_handler(tauri::generate_handler![get_cursor_position, get_platform])
         // 注册自定义 localfile:// 协议，带有 CORS 头
         .register_uri_scheme_protocol("localfile", |_app, request| {
             handle_localfile_protocol(request)
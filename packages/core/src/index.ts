@@ -90,6 +90,21 @@ export {
   createCognitiveTools,
   createCommunicationTools,
   createPluginTools,
+  validatePluginReturn,
+  isPluginSupportedOn,
+  normalizePlatform,
+  shouldFallback,
+  buildToolDescription,
+  applyResultProcessors,
+  createRedactProcessor,
+  createTruncateProcessor,
+  DEFAULT_REDACT_PATTERNS,
+  HEX_SECRET_PATTERN,
+  runWithInterceptors,
+  rejectToolCall,
+  PluginToolRejection,
+  createAuditInterceptor,
+  createRateLimitInterceptor,
 } from './tools'
 
 // 新增类型导出
@@ -100,6 +115,17 @@ export type {
   ModelAnalysisResult,
   LLMAnalyzerConfig,
   PluginToolDefinition,
+  PluginReturnSchema,
   PluginManifest,
   PluginToolExecutor,
+  PluginToolResult,
+  PluginFallbackCondition,
+  CreatePluginToolsOptions,
+  PluginToolConcurrency,
+  PluginToolExample,
+  PluginResultProcessor,
+  PluginResultContext,
+  PluginProcessedResult,
+  PluginToolInterceptor,
+  PluginToolCallContext,
 } from './tools'
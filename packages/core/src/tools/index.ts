@@ -38,5 +38,42 @@ export { createCognitiveTools } from './cognitive.tools'
 export { createCommunicationTools } from './communication.tools'
 
 // 插件工具适配层
-export { createPluginTools } from './plugin.tools'
-export type { PluginToolDefinition, PluginManifest, PluginToolExecutor } from './plugin.tools'
\ No newline at end of file
+export {
+  runWithInterceptors,
+  rejectToolCall,
+  PluginToolRejection,
+  createAuditInterceptor,
+  createRateLimitInterceptor,
+} from './pluginInterceptors'
+export type { PluginToolInterceptor, PluginToolCallContext } from './pluginInterceptors'
+export {
+  applyResultProcessors,
+  createRedactProcessor,
+  createTruncateProcessor,
+  DEFAULT_REDACT_PATTERNS,
+  HEX_SECRET_PATTERN,
+} from './pluginResultProcessors'
+export type {
+  PluginResultProcessor,
+  PluginResultContext,
+  PluginProcessedResult,
+} from './pluginResultProcessors'
+export {
+  createPluginTools,
+  validatePluginReturn,
+  isPluginSupportedOn,
+  normalizePlatform,
+  shouldFallback,
+  buildToolDescription,
+} from './plugin.tools'
+export type {
+  PluginToolDefinition,
+  PluginReturnSchema,
+  PluginManifest,
+  PluginToolExecutor,
+  PluginToolResult,
+  PluginFallbackCondition,
+  CreatePluginToolsOptions,
+  PluginToolConcurrency,
+  PluginToolExample,
+} from './plugin.tools'
\ No newline at end of file
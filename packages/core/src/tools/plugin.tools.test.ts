@@ -0,0 +1,484 @@
+/**
+ * 插件工具适配层测试
+ */
+
+import { describe, it, expect, vi } from 'vitest'
+import {
+  validatePluginReturn,
+  isPluginSupportedOn,
+  normalizePlatform,
+  createPluginTools,
+  shouldFallback,
+  buildToolDescription,
+} from './plugin.tools'
+
+describe('validatePluginReturn', () => {
+  it('should accept matching primitive types', () => {
+    expect(validatePluginReturn({ type: 'string' }, 'hello')).toEqual([])
+    expect(validatePluginReturn({ type: 'boolean' }, false)).toEqual([])
+  })
+
+  it('should accept integers where number is declared', () => {
+    expect(validatePluginReturn({ type: 'number' }, 3)).toEqual([])
+    expect(validatePluginReturn({ type: 'integer' }, 3.5)).toHaveLength(1)
+  })
+
+  it('should accept any of a type array', () => {
+    const schema = { type: ['string', 'null'] }
+    expect(validatePluginReturn(schema, 'text')).toEqual([])
+    expect(validatePluginReturn(schema, null)).toEqual([])
+    expect(validatePluginReturn(schema, 1)).toEqual(['data: 期望 string | null，实际为 integer'])
+  })
+
+  it('should report missing required fields', () => {
+    const schema = { type: 'object', required: ['page', 'text'] }
+    expect(validatePluginReturn(schema, { page: 1 })).toEqual(['data.text: 缺少必填字段'])
+  })
+
+  it('should validate nested properties and array items', () => {
+    const schema = {
+      type: 'object',
+      properties: {
+        pages: { type: 'array', items: { type: 'object', required: ['page'] } },
+      },
+    }
+    expect(validatePluginReturn(schema, { pages: [{ page: 1 }, {}] })).toEqual([
+      'data.pages[1].page: 缺少必填字段',
+    ])
+  })
+
+  it('should not descend into a value with the wrong type', () => {
+    const schema = { type: 'object', required: ['page'] }
+    expect(validatePluginReturn(schema, [])).toEqual(['data: 期望 object，实际为 array'])
+  })
+
+  it('should accept anything when no type is declared', () => {
+    expect(validatePluginReturn({}, { any: 'thing' })).toEqual([])
+  })
+})
+
+describe('isPluginSupportedOn', () => {
+  const manifest = (platforms?: string[]) => ({ id: 'demo', name: 'Demo', tools: [], platforms })
+
+  it('should treat missing or empty platforms as all platforms', () => {
+    expect(isPluginSupportedOn(manifest(), 'linux')).toBe(true)
+    expect(isPluginSupportedOn(manifest([]), 'linux')).toBe(true)
+  })
+
+  it('should match platform names case-insensitively and by alias', () => {
+    expect(normalizePlatform('macOS')).toBe('macos')
+    expect(normalizePlatform('darwin')).toBe('macos')
+    expect(normalizePlatform('Win32')).toBe('windows')
+    expect(isPluginSupportedOn(manifest(['macOS']), 'macos')).toBe(true)
+    expect(isPluginSupportedOn(manifest(['darwin']), 'macos')).toBe(true)
+  })
+
+  it('should reject platforms not listed', () => {
+    expect(isPluginSupportedOn(manifest(['macos']), 'linux')).toBe(false)
+  })
+})
+
+describe('createPluginTools available_on', () => {
+  const manifest = {
+    id: 'demo',
+    name: 'Demo',
+    tools: [
+      { name: 'everywhere', description: '全平台' },
+      { name: 'mac_only', description: '仅 macOS', available_on: ['macOS'] },
+    ],
+  }
+  const executor = async () => ({ success: true, data: null })
+
+  it('should keep every tool when no platform is given', () => {
+    expect(createPluginTools(manifest, executor)).toHaveLength(2)
+  })
+
+  it('should drop tools unavailable on the current platform', () => {
+    const names = createPluginTools(manifest, executor, { platform: 'linux' }).map((t) => t.name)
+    expect(names).toEqual(['demo__everywhere'])
+  })
+
+  it('should match available_on case-insensitively', () => {
+    expect(createPluginTools(manifest, executor, { platform: 'macos' })).toHaveLength(2)
+  })
+})
+
+describe('shouldFallback', () => {
+  it('should fall back on error only when configured', () => {
+    const failed = { success: false, data: null, error: 'boom' }
+    expect(shouldFallback(failed, ['error'])).toBe(true)
+    expect(shouldFallback(failed, ['empty'])).toBe(false)
+  })
+
+  it('should treat null, blank strings, empty arrays and objects as empty', () => {
+    for (const data of [null, undefined, '  ', [], {}]) {
+      expect(shouldFallback({ success: true, data }, ['empty'])).toBe(true)
+    }
+  })
+
+  it('should not treat falsy scalars as empty', () => {
+    for (const data of [0, false, 'x', [0], { a: 1 }]) {
+      expect(shouldFallback({ success: true, data }, ['empty'])).toBe(false)
+    }
+  })
+
+  it('should fall back only on errors matching an error condition', () => {
+    const conditions = [{ error: '^E_SCANNED' }]
+    expect(shouldFallback({ success: false, data: null, error: 'E_SCANNED: 无文本层' }, conditions)).toBe(true)
+    expect(shouldFallback({ success: false, data: null, error: 'E_NOT_FOUND' }, conditions)).toBe(false)
+    expect(shouldFallback({ success: false, data: null }, conditions)).toBe(false)
+  })
+
+  it('should treat an invalid error pattern as a substring', () => {
+    const conditions = [{ error: 'code(' }]
+    expect(shouldFallback({ success: false, data: null, error: 'bad code( here' }, conditions)).toBe(true)
+  })
+
+  it('should not apply error conditions to successful results', () => {
+    expect(shouldFallback({ success: true, data: [] }, [{ error: '.*' }])).toBe(false)
+  })
+
+  it('should ignore empty results unless empty is a condition', () => {
+    expect(shouldFallback({ success: true, data: [] }, ['error'])).toBe(false)
+  })
+})
+
+describe('createPluginTools fallback_tool', () => {
+  const makeManifest = (fallback_tool: string) => ({
+    id: 'pdf',
+    name: 'PDF',
+    tools: [
+      {
+        name: 'read_page',
+        description: '读取页面文本',
+        parameters: { page: { type: 'number', required: true }, raw: { type: 'boolean' } },
+        fallback_tool,
+      },
+      {
+        name: 'ocr_page',
+        description: 'OCR 识别页面',
+        parameters: { page: { type: 'number', required: true } },
+      },
+    ],
+  })
+
+  it('should call the fallback with only its declared arguments', async () => {
+    const executor = vi.fn(async (_pluginId: string, toolName: string) =>
+      toolName === 'read_page' ? { success: false, data: null } : { success: true, data: 'text' }
+    )
+    const [readPage] = createPluginTools(makeManifest('ocr_page'), executor)
+    const output = JSON.parse(await readPage.invoke({ page: 2, raw: true }))
+
+    expect(executor).toHaveBeenLastCalledWith('pdf', 'ocr_page', { page: 2 })
+    expect(output.data).toBe('text')
+    expect(output.fallback).toMatchObject({ from: 'read_page', to: 'ocr_page', reason: '主工具调用失败' })
+  })
+
+  it('should return the primary result when the fallback does not exist', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const executor = vi.fn(async () => ({ success: false, data: null, error: 'boom' }))
+    const [readPage] = createPluginTools(makeManifest('missing'), executor)
+    const output = JSON.parse(await readPage.invoke({ page: 1 }))
+
+    expect(executor).toHaveBeenCalledTimes(1)
+    expect(output).toEqual({ success: false, data: null, error: 'boom' })
+    warn.mockRestore()
+  })
+
+  it('should ignore a fallback pointing at the tool itself', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const executor = vi.fn(async () => ({ success: false, data: null }))
+    const [readPage] = createPluginTools(makeManifest('read_page'), executor)
+    await readPage.invoke({ page: 1 })
+
+    expect(executor).toHaveBeenCalledTimes(1)
+    warn.mockRestore()
+  })
+})
+
+describe('createPluginTools concurrency', () => {
+  const manifest = {
+    id: 'notes',
+    name: 'Notes',
+    tools: [
+      { name: 'create_note', description: '创建备忘录', concurrency: 'serial' as const },
+      { name: 'list_notes', description: '列出备忘录' },
+    ],
+  }
+
+  // 记录调用顺序，并由测试手动决定每次调用何时完成
+  const makeExecutor = () => {
+    const started: string[] = []
+    const pending: Array<() => void> = []
+    const executor = vi.fn(
+      (_pluginId: string, toolName: string) =>
+        new Promise<{ success: boolean; data: unknown }>((resolve) => {
+          started.push(toolName)
+          pending.push(() => resolve({ success: true, data: toolName }))
+        })
+    )
+    return { executor, started, pending }
+  }
+
+  const flush = () => new Promise((resolve) => setTimeout(resolve, 20))
+
+  it('should run serial tools of the same plugin one at a time', async () => {
+    const { executor, started, pending } = makeExecutor()
+    const [createNote] = createPluginTools(manifest, executor)
+
+    const first = createNote.invoke({})
+    const second = createNote.invoke({})
+    await flush()
+    expect(started).toEqual(['create_note'])
+
+    pending[0]()
+    await first
+    await flush()
+    expect(started).toEqual(['create_note', 'create_note'])
+
+    pending[1]()
+    await second
+  })
+
+  it('should not queue parallel tools behind serial ones', async () => {
+    const { executor, started, pending } = makeExecutor()
+    const [createNote, listNotes] = createPluginTools(manifest, executor)
+
+    const serial = createNote.invoke({})
+    const parallel = listNotes.invoke({})
+    await flush()
+    expect(started).toEqual(['create_note', 'list_notes'])
+
+    pending.forEach((resolve) => resolve())
+    await Promise.all([serial, parallel])
+  })
+})
+
+describe('createPluginTools idempotency', () => {
+  const manifest = {
+    id: 'notes',
+    name: 'Notes',
+    tools: [
+      {
+        name: 'create_note',
+        description: '创建备忘录',
+        parameters: { title: { type: 'string', required: true } },
+        idempotency: true,
+      },
+    ],
+  }
+
+  it('should run a key once and replay the first result', async () => {
+    const executor = vi.fn(async () => ({ success: true, data: { id: 1 } }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    const first = JSON.parse(await createNote.invoke({ title: 'a', idempotency_key: 'k' }))
+    const second = JSON.parse(await createNote.invoke({ title: 'a', idempotency_key: 'k' }))
+
+    expect(executor).toHaveBeenCalledTimes(1)
+    expect(executor).toHaveBeenCalledWith('notes', 'create_note', { title: 'a' })
+    expect(first.idempotent_replay).toBeUndefined()
+    expect(second).toMatchObject({ success: true, data: { id: 1 }, idempotent_replay: true })
+  })
+
+  it('should share an in-flight call between concurrent duplicates', async () => {
+    const executor = vi.fn(async () => ({ success: true, data: null }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    await Promise.all([
+      createNote.invoke({ title: 'b', idempotency_key: 'k' }),
+      createNote.invoke({ title: 'b', idempotency_key: 'k' }),
+    ])
+
+    expect(executor).toHaveBeenCalledTimes(1)
+  })
+
+  it('should not cache failed calls', async () => {
+    const executor = vi.fn(async () => ({ success: false, data: null, error: 'denied' }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    await createNote.invoke({ title: 'c', idempotency_key: 'k' })
+    await createNote.invoke({ title: 'c', idempotency_key: 'k' })
+
+    expect(executor).toHaveBeenCalledTimes(2)
+  })
+
+  it('should execute again once the window has passed', async () => {
+    const now = vi.spyOn(Date, 'now').mockReturnValue(1_000_000)
+    try {
+      const executor = vi.fn(async () => ({ success: true, data: null }))
+      const [createNote] = createPluginTools(manifest, executor, { idempotencyWindowMs: 1000 })
+
+      await createNote.invoke({ title: 'd', idempotency_key: 'k' })
+      now.mockReturnValue(1_001_001)
+      await createNote.invoke({ title: 'd', idempotency_key: 'k' })
+
+      expect(executor).toHaveBeenCalledTimes(2)
+    } finally {
+      now.mockRestore()
+    }
+  })
+
+  it('should reject a reused key with different arguments', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const executor = vi.fn(async () => ({ success: true, data: { id: 1 } }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    await createNote.invoke({ title: 'a', idempotency_key: 'k' })
+    const output = JSON.parse(await createNote.invoke({ title: 'b', idempotency_key: 'k' }))
+
+    expect(executor).toHaveBeenCalledTimes(1)
+    expect(output).toEqual({ success: false, data: null, error: 'idempotency_key 已用于不同参数' })
+    warn.mockRestore()
+  })
+
+  it('should not expire a call that is still in flight', async () => {
+    const now = vi.spyOn(Date, 'now').mockReturnValue(1_000_000)
+    try {
+      let finish: () => void = () => {}
+      const executor = vi.fn(
+        () =>
+          new Promise<{ success: boolean; data: unknown }>((resolve) => {
+            finish = () => resolve({ success: true, data: null })
+          })
+      )
+      const [createNote] = createPluginTools(manifest, executor, { idempotencyWindowMs: 1000 })
+
+      const first = createNote.invoke({ title: 'f', idempotency_key: 'k' })
+      await new Promise((resolve) => setTimeout(resolve, 20))
+      now.mockReturnValue(1_005_000)
+      const second = createNote.invoke({ title: 'f', idempotency_key: 'k' })
+      await new Promise((resolve) => setTimeout(resolve, 20))
+      finish()
+      await Promise.all([first, second])
+
+      expect(executor).toHaveBeenCalledTimes(1)
+    } finally {
+      now.mockRestore()
+    }
+  })
+
+  it('should execute every call without a key', async () => {
+    const executor = vi.fn(async () => ({ success: true, data: null }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    await createNote.invoke({ title: 'e' })
+    await createNote.invoke({ title: 'e' })
+
+    expect(executor).toHaveBeenCalledTimes(2)
+  })
+})
+
+describe('buildToolDescription', () => {
+  const manifest = { id: 'pdf', name: 'PDF', tools: [] }
+
+  it('should only prefix the plugin name when nothing else is declared', () => {
+    expect(buildToolDescription(manifest, { name: 'open_pdf', description: '打开 PDF' })).toBe(
+      '[PDF] 打开 PDF'
+    )
+  })
+
+  it('should append the returns schema', () => {
+    const description = buildToolDescription(manifest, {
+      name: 'open_pdf',
+      description: '打开 PDF',
+      returns: { type: 'object', required: ['pages'] },
+    })
+    expect(description).toBe('[PDF] 打开 PDF\n返回结构:\n{"type":"object","required":["pages"]}')
+  })
+
+  it('should render bare and scenario examples', () => {
+    const description = buildToolDescription(manifest, {
+      name: 'open_pdf',
+      description: '打开 PDF',
+      examples: [
+        { path: '/Users/x/a.pdf' },
+        { args: { path: '/Users/x/b.pdf' }, scenario: '用户要求打开下载目录里的论文' },
+      ],
+    })
+    expect(description).toBe(
+      '[PDF] 打开 PDF\n调用示例:\n' +
+        '{"path":"/Users/x/a.pdf"}\n' +
+        '用户要求打开下载目录里的论文: {"path":"/Users/x/b.pdf"}'
+    )
+  })
+
+  it('should treat an example as bare args when the tool has an args parameter', () => {
+    const description = buildToolDescription(manifest, {
+      name: 'run',
+      description: '运行',
+      parameters: { args: { type: 'string' } },
+      examples: [{ args: { nested: true } }],
+    })
+    expect(description.endsWith('调用示例:\n{"args":{"nested":true}}')).toBe(true)
+  })
+
+  it('should put returns before examples', () => {
+    const description = buildToolDescription(manifest, {
+      name: 'open_pdf',
+      description: '打开 PDF',
+      returns: { type: 'string' },
+      examples: [{ path: 'a.pdf' }],
+    })
+    expect(description.indexOf('返回结构')).toBeLessThan(description.indexOf('调用示例'))
+  })
+})
+
+describe('createPluginTools dry_run', () => {
+  const manifest = {
+    id: 'notes',
+    name: 'Notes',
+    tools: [
+      {
+        name: 'create_note',
+        description: '创建备忘录',
+        parameters: { title: { type: 'string', required: true } },
+        supports_dry_run: true,
+        idempotency: true,
+        fallback_tool: 'create_note_legacy',
+      },
+      {
+        name: 'create_note_legacy',
+        description: '旧版创建备忘录',
+        parameters: { title: { type: 'string', required: true } },
+      },
+      { name: 'list_notes', description: '列出备忘录' },
+    ],
+  }
+
+  it('should forward dry_run to the plugin and mark the output', async () => {
+    const executor = vi.fn(async () => ({ success: true, data: '将创建备忘录 a' }))
+    const [createNote] = createPluginTools(manifest, executor)
+    const output = JSON.parse(await createNote.invoke({ title: 'a', dry_run: true }))
+
+    expect(executor).toHaveBeenCalledWith('notes', 'create_note', { title: 'a', dry_run: true })
+    expect(output).toMatchObject({ success: true, dry_run: true })
+  })
+
+  it('should only expose dry_run on tools that support it', () => {
+    const [createNote, , listNotes] = createPluginTools(manifest, async () => ({ success: true, data: null }))
+    const shapeKeys = (tool: typeof createNote) =>
+      Object.keys((tool.schema as unknown as { shape: Record<string, unknown> }).shape)
+    expect(shapeKeys(createNote)).toContain('dry_run')
+    expect(shapeKeys(listNotes)).not.toContain('dry_run')
+  })
+
+  it('should not fall back to a tool without dry_run support', async () => {
+    const executor = vi.fn(async () => ({ success: false, data: null, error: 'boom' }))
+    const [createNote] = createPluginTools(manifest, executor)
+    await createNote.invoke({ title: 'a', dry_run: true })
+
+    expect(executor).toHaveBeenCalledTimes(1)
+  })
+
+  it('should not let a dry run consume the idempotency key', async () => {
+    const executor = vi.fn(async () => ({ success: true, data: null }))
+    const [createNote] = createPluginTools(manifest, executor)
+
+    await createNote.invoke({ title: 'a', dry_run: true, idempotency_key: 'k' })
+    const output = JSON.parse(await createNote.invoke({ title: 'a', idempotency_key: 'k' }))
+
+    expect(executor).toHaveBeenCalledTimes(2)
+    expect(output.idempotent_replay).toBeUndefined()
+  })
+})
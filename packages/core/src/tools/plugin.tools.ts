@@ -5,33 +5,342 @@
 import { tool } from '@langchain/core/tools'
 import type { StructuredToolInterface } from '@langchain/core/tools'
 import { z } from 'zod'
+import { applyResultProcessors } from './pluginResultProcessors'
+import type { PluginResultProcessor, PluginProcessedResult } from './pluginResultProcessors'
+import { runWithInterceptors } from './pluginInterceptors'
+import type { PluginToolInterceptor } from './pluginInterceptors'
 
 export interface PluginToolDefinition {
   name: string
   description: string
   parameters?: Record<string, { type: string; description?: string; required?: boolean }>
+  /** 返回值 data 的 JSON Schema（可选），用于校验插件实际返回 */
+  returns?: PluginReturnSchema
+  /** 调用示例，随工具描述一起提供给 LLM；可直接写参数对象，或带场景说明的 { args, scenario } */
+  examples?: Array<PluginToolExample | Record<string, unknown>>
+  /** 备选工具名（同一插件内），主工具满足降级条件时自动改调它 */
+  fallback_tool?: string
+  /** 触发降级的条件，默认 ['error'] */
+  fallback_on?: PluginFallbackCondition[]
+  /** 工具可用的平台（macos / windows / linux），缺省表示全平台 */
+  available_on?: string[]
+  /** 并发级别：serial 的工具在同一插件（同一次 createPluginTools）内排队执行，默认 parallel */
+  concurrency?: PluginToolConcurrency
+  /** 是否接受 idempotency_key 参数，相同 key 在时间窗口内的重复调用直接返回首次结果 */
+  idempotency?: boolean
+  /** 是否支持 dry_run 参数：为 true 时插件只返回将要执行的操作描述而不真正执行 */
+  supports_dry_run?: boolean
+}
+
+export type PluginToolConcurrency = 'parallel' | 'serial'
+
+export interface PluginToolExample {
+  args: Record<string, unknown>
+  /** 预期场景说明 */
+  scenario?: string
+}
+
+/**
+ * 降级条件：error 为任意调用失败，empty 为成功但返回数据为空，
+ * { error } 为错误信息匹配该正则（源码字符串）的调用失败
+ */
+export type PluginFallbackCondition = 'error' | 'empty' | { error: string }
+
+export interface PluginReturnSchema {
+  /** 类型名，或多个允许的类型（如 ['string', 'null']） */
+  type?: string | string[]
+  properties?: Record<string, PluginReturnSchema>
+  required?: string[]
+  items?: PluginReturnSchema
+  description?: string
 }
 
 export interface PluginManifest {
   id: string
   name: string
   tools: PluginToolDefinition[]
+  /** 支持的平台（macos / windows / linux），缺省表示全平台 */
+  platforms?: string[]
+}
+
+export interface PluginToolResult {
+  success: boolean
+  data: unknown
+  error?: string
 }
 
 export type PluginToolExecutor = (
   pluginId: string,
   toolName: string,
   args: Record<string, unknown>
-) => Promise<{ success: boolean; data: unknown; error?: string }>
+) => Promise<PluginToolResult>
+
+const PLATFORM_ALIASES: Record<string, string> = {
+  darwin: 'macos',
+  mac: 'macos',
+  osx: 'macos',
+  win32: 'windows',
+  win: 'windows',
+}
+
+/**
+ * 规范化平台名：忽略大小写，并把 darwin / win32 等别名映射为 macos / windows
+ */
+export function normalizePlatform(platform: string): string {
+  const name = platform.trim().toLowerCase()
+  return PLATFORM_ALIASES[name] ?? name
+}
+
+/**
+ * 判断平台列表是否包含当前平台，空列表表示全平台
+ */
+function matchesPlatform(platforms: string[] | undefined, platform: string): boolean {
+  if (!platforms || platforms.length === 0) return true
+  const current = normalizePlatform(platform)
+  return platforms.some((name) => normalizePlatform(name) === current)
+}
+
+/**
+ * 判断插件是否支持当前平台
+ */
+export function isPluginSupportedOn(manifest: PluginManifest, platform: string): boolean {
+  return matchesPlatform(manifest.platforms, platform)
+}
+
+/**
+ * 创建串行队列：任务排到队尾，前一个任务结束（无论成功失败）后才执行
+ */
+function createSerialQueue() {
+  let tail: Promise<unknown> = Promise.resolve()
+  return <T>(task: () => Promise<T>): Promise<T> => {
+    const next = tail.then(task, task)
+    tail = next.catch(() => undefined)
+    return next
+  }
+}
+
+interface PluginToolOutput extends PluginProcessedResult {
+  fallback?: { from: string; to: string; reason: string; note: string }
+  /** 本次为预览调用，未真正执行 */
+  dry_run?: boolean
+}
+
+interface IdempotencyEntry {
+  /** 首次调用参数的指纹，同一 key 只能用于相同参数 */
+  fingerprint: string
+  /** 结果返回后才开始计时，进行中的调用没有过期时间 */
+  expiresAt?: number
+  output: Promise<PluginToolOutput>
+}
+
+/**
+ * 键排序后的 JSON 序列化，用作参数指纹
+ */
+function stableStringify(value: unknown): string {
+  if (Array.isArray(value)) {
+    return `[${value.map(stableStringify).join(',')}]`
+  }
+  if (value && typeof value === 'object') {
+    const entries = Object.entries(value)
+      .filter(([, item]) => item !== undefined)
+      .sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0))
+    return `{${entries.map(([key, item]) => `${JSON.stringify(key)}:${stableStringify(item)}`).join(',')}}`
+  }
+  return JSON.stringify(value) ?? 'null'
+}
+
+/**
+ * 清理已过期的幂等缓存，进行中的调用不会被清理
+ */
+function pruneIdempotencyCache(cache: Map<string, IdempotencyEntry>, now: number): void {
+  for (const [key, entry] of cache) {
+    if (entry.expiresAt !== undefined && entry.expiresAt <= now) cache.delete(key)
+  }
+}
+
+/**
+ * 获取值对应的 JSON Schema 类型名
+ */
+function jsonTypeOf(value: unknown): string {
+  if (value === null) return 'null'
+  if (Array.isArray(value)) return 'array'
+  if (typeof value === 'number') return Number.isInteger(value) ? 'integer' : 'number'
+  return typeof value
+}
+
+/**
+ * 按声明的返回 schema 校验数据，返回不匹配的描述列表
+ * 只检查类型、必填字段与嵌套结构，不做完整的 JSON Schema 校验
+ */
+export function validatePluginReturn(
+  schema: PluginReturnSchema,
+  value: unknown,
+  path = 'data'
+): string[] {
+  const problems: string[] = []
+
+  if (schema.type) {
+    const actual = jsonTypeOf(value)
+    const expected = Array.isArray(schema.type) ? schema.type : [schema.type]
+    const matches = expected.some(
+      (type) => type === actual || (type === 'number' && actual === 'integer')
+    )
+    if (!matches) {
+      problems.push(`${path}: 期望 ${expected.join(' | ')}，实际为 ${actual}`)
+      return problems
+    }
+  }
+
+  if (value && typeof value === 'object' && !Array.isArray(value)) {
+    const obj = value as Record<string, unknown>
+    for (const key of schema.required ?? []) {
+      if (!(key in obj)) {
+        problems.push(`${path}.${key}: 缺少必填字段`)
+      }
+    }
+    for (const [key, child] of Object.entries(schema.properties ?? {})) {
+      if (key in obj) {
+        problems.push(...validatePluginReturn(child, obj[key], `${path}.${key}`))
+      }
+    }
+  }
+
+  if (Array.isArray(value) && schema.items) {
+    value.forEach((item, index) => {
+      problems.push(...validatePluginReturn(schema.items!, item, `${path}[${index}]`))
+    })
+  }
+
+  return problems
+}
+
+/**
+ * 判断返回数据是否为空（null、空字符串、空数组、空对象）
+ */
+function isEmptyData(data: unknown): boolean {
+  if (data === null || data === undefined) return true
+  if (typeof data === 'string') return data.trim() === ''
+  if (Array.isArray(data)) return data.length === 0
+  if (typeof data === 'object') return Object.keys(data).length === 0
+  return false
+}
+
+/**
+ * 判断错误信息是否匹配条件中的正则，正则无效时按子串匹配
+ */
+function matchesErrorPattern(pattern: string, error: string | undefined): boolean {
+  if (error === undefined) return false
+  try {
+    return new RegExp(pattern).test(error)
+  } catch {
+    return error.includes(pattern)
+  }
+}
+
+/**
+ * 判断结果是否满足降级条件
+ */
+export function shouldFallback(result: PluginToolResult, conditions: PluginFallbackCondition[]): boolean {
+  if (!result.success) {
+    return conditions.some(
+      (condition) =>
+        condition === 'error' ||
+        (typeof condition === 'object' && matchesErrorPattern(condition.error, result.error))
+    )
+  }
+  return conditions.includes('empty') && isEmptyData(result.data)
+}
+
+/**
+ * 按工具声明的 parameters 筛选参数，未声明的参数不会传给该工具
+ * 支持 dry_run 的工具保留 dry_run 参数
+ */
+function pickDeclaredArgs(
+  toolDef: PluginToolDefinition,
+  args: Record<string, unknown>
+): Record<string, unknown> {
+  const declared = toolDef.parameters ?? {}
+  return Object.fromEntries(
+    Object.entries(args).filter(
+      ([key]) => key in declared || (key === 'dry_run' && toolDef.supports_dry_run)
+    )
+  )
+}
+
+/**
+ * 把示例统一为 { args, scenario }
+ * 只有 args / scenario 两个键且工具本身没有名为 args 的参数时才视为带说明的示例，其余按参数对象处理
+ */
+function normalizeExample(
+  toolDef: PluginToolDefinition,
+  example: PluginToolExample | Record<string, unknown>
+): PluginToolExample {
+  const keys = Object.keys(example)
+  const isStructured =
+    !(toolDef.parameters && 'args' in toolDef.parameters) &&
+    typeof example.args === 'object' &&
+    example.args !== null &&
+    keys.every((key) => key === 'args' || key === 'scenario')
+  return isStructured ? (example as PluginToolExample) : { args: example }
+}
+
+/**
+ * 生成提供给 LLM 的工具描述，附带返回结构与调用示例
+ */
+export function buildToolDescription(manifest: PluginManifest, toolDef: PluginToolDefinition): string {
+  let description = `[${manifest.name}] ${toolDef.description}`
+  if (toolDef.returns) {
+    description += `\n返回结构:\n${JSON.stringify(toolDef.returns)}`
+  }
+  if (toolDef.examples && toolDef.examples.length > 0) {
+    const examples = toolDef.examples
+      .map((example) => {
+        const { args, scenario } = normalizeExample(toolDef, example)
+        return scenario ? `${scenario}: ${JSON.stringify(args)}` : JSON.stringify(args)
+      })
+      .join('\n')
+    description += `\n调用示例:\n${examples}`
+  }
+  return description
+}
+
+export interface CreatePluginToolsOptions {
+  /** 当前平台，提供时过滤掉 available_on 不包含该平台的工具 */
+  platform?: string
+  /** 是否按 returns 校验返回值并告警，建议仅在开发模式开启 */
+  validateReturns?: boolean
+  /** 结果后处理器，按顺序应用于匹配的工具结果 */
+  resultProcessors?: PluginResultProcessor[]
+  /** 工具调用拦截器，包裹每次插件工具执行（含备选工具） */
+  interceptors?: PluginToolInterceptor[]
+  /** 幂等键的有效时间窗口（毫秒），默认 60 秒 */
+  idempotencyWindowMs?: number
+}
 
 /**
  * 将插件 manifest 中的工具定义转换为 LangChain 工具
  */
 export function createPluginTools(
   manifest: PluginManifest,
-  executor: PluginToolExecutor
+  executor: PluginToolExecutor,
+  options: CreatePluginToolsOptions = {}
 ): StructuredToolInterface[] {
-  return manifest.tools.map((toolDef) => {
+  const {
+    platform,
+    validateReturns = false,
+    resultProcessors = [],
+    interceptors = [],
+    idempotencyWindowMs = 60_000,
+  } = options
+  const availableTools = manifest.tools.filter(
+    (toolDef) => !platform || matchesPlatform(toolDef.available_on, platform)
+  )
+  // 每次创建（即每个插件）独立的串行队列，插件重新加载时随之重置
+  const runSerial = createSerialQueue()
+  // 幂等键缓存，key 为 `${插件工具名}:${idempotency_key}`
+  const idempotencyCache = new Map<string, IdempotencyEntry>()
+
+  return availableTools.map((toolDef) => {
     const schemaShape: Record<string, z.ZodTypeAny> = {}
 
     if (toolDef.parameters) {
@@ -63,23 +372,147 @@ export function createPluginTools(
       }
     }
 
+    if (toolDef.idempotency) {
+      schemaShape.idempotency_key = z
+        .string()
+        .describe('幂等键：重试同一操作时传入相同的值，短时间内不会重复执行')
+        .optional()
+    }
+
+    if (toolDef.supports_dry_run) {
+      schemaShape.dry_run = z
+        .boolean()
+        .describe('预览模式：为 true 时只返回将要执行的操作描述，不真正执行')
+        .optional()
+    }
+
     const toolName = `${manifest.id}__${toolDef.name}`
 
+    // 备选工具只在本插件当前可用的工具中查找，不允许指向自身
+    const fallbackName = toolDef.fallback_tool
+    const fallbackDef =
+      fallbackName && fallbackName !== toolDef.name
+        ? availableTools.find((def) => def.name === fallbackName)
+        : undefined
+    if (fallbackName && !fallbackDef) {
+      console.warn(`[Plugins] ${toolName} 的备选工具 ${fallbackName} 不存在或不可用，已忽略`)
+    }
+
+    const execute = async (
+      def: PluginToolDefinition,
+      args: Record<string, unknown>
+    ): Promise<PluginToolResult> => {
+      try {
+        return await executor(manifest.id, def.name, args)
+      } catch (error) {
+        return {
+          success: false,
+          data: null,
+          error: error instanceof Error ? error.message : String(error),
+        }
+      }
+    }
+
+    const run = (def: PluginToolDefinition, args: Record<string, unknown>) =>
+      runWithInterceptors(
+        interceptors,
+        { pluginId: manifest.id, toolName: def.name },
+        args,
+        (finalArgs) =>
+          def.concurrency === 'serial'
+            ? runSerial(() => execute(def, finalArgs))
+            : execute(def, finalArgs)
+      )
+
+    const checkReturn = (def: PluginToolDefinition, result: PluginToolResult) => {
+      if (!validateReturns || !def.returns || !result.success) return
+      const problems = validatePluginReturn(def.returns, result.data)
+      if (problems.length > 0) {
+        console.warn(`[Plugins] ${manifest.id}__${def.name} 返回值与声明的 schema 不符:`, problems)
+      }
+    }
+
+    const invokeTool = async (args: Record<string, unknown>): Promise<PluginToolOutput> => {
+      const context = { pluginId: manifest.id, toolName: toolDef.name }
+      const isDryRun = args.dry_run === true
+      const result = await run(toolDef, args)
+      if (!isDryRun) checkReturn(toolDef, result)
+
+      // 预览调用不能降级到不支持 dry_run 的备选工具，否则会真正执行
+      if (
+        fallbackDef &&
+        (!isDryRun || fallbackDef.supports_dry_run) &&
+        shouldFallback(result, toolDef.fallback_on ?? ['error'])
+      ) {
+        console.log(`[Plugins] ${toolName} 降级到备选工具 ${fallbackDef.name}`)
+        const fallbackResult = await run(fallbackDef, pickDeclaredArgs(fallbackDef, args))
+        if (!isDryRun) checkReturn(fallbackDef, fallbackResult)
+        const processed = await applyResultProcessors(resultProcessors, fallbackResult, {
+          ...context,
+          toolName: fallbackDef.name,
+        })
+        return {
+          ...processed,
+          fallback: {
+            from: toolDef.name,
+            to: fallbackDef.name,
+            reason: result.success ? '主工具返回为空' : (result.error ?? '主工具调用失败'),
+            note: '已降级到备选方案',
+          },
+          ...(isDryRun ? { dry_run: true } : {}),
+        }
+      }
+
+      const processed = await applyResultProcessors(resultProcessors, result, context)
+      return isDryRun ? { ...processed, dry_run: true } : processed
+    }
+
     return tool(
       async (args) => {
-        try {
-          const result = await executor(manifest.id, toolDef.name, args)
-          return JSON.stringify(result)
-        } catch (error) {
-          return JSON.stringify({
-            success: false,
-            error: error instanceof Error ? error.message : String(error),
-          })
+        if (!toolDef.idempotency) {
+          return JSON.stringify(await invokeTool(args))
+        }
+
+        const { idempotency_key: key, ...toolArgs } = args as Record<string, unknown>
+        // 预览调用不产生副作用，不占用也不命中幂等键
+        if (typeof key !== 'string' || key === '' || toolArgs.dry_run === true) {
+          return JSON.stringify(await invokeTool(toolArgs))
+        }
+
+        pruneIdempotencyCache(idempotencyCache, Date.now())
+        const cacheKey = `${toolName}:${key}`
+        const fingerprint = stableStringify(toolArgs)
+        const cached = idempotencyCache.get(cacheKey)
+        if (cached) {
+          if (cached.fingerprint !== fingerprint) {
+            console.warn(`[Plugins] ${toolName} 幂等键 ${key} 已用于不同参数，拒绝执行`)
+            return JSON.stringify({
+              success: false,
+              data: null,
+              error: 'idempotency_key 已用于不同参数',
+            })
+          }
+          console.log(`[Plugins] ${toolName} 幂等键 ${key} 命中，返回首次结果`)
+          return JSON.stringify({ ...(await cached.output), idempotent_replay: true })
+        }
+
+        // 缓存进行中的 Promise，并发的重复调用也只执行一次
+        const entry: IdempotencyEntry = { fingerprint, output: invokeTool(toolArgs) }
+        idempotencyCache.set(cacheKey, entry)
+        const output = await entry.output
+        if (idempotencyCache.get(cacheKey) === entry) {
+          if (output.success) {
+            entry.expiresAt = Date.now() + idempotencyWindowMs
+          } else {
+            // 失败的调用不缓存，允许用同一 key 重试
+            idempotencyCache.delete(cacheKey)
+          }
         }
+        return JSON.stringify(output)
       },
       {
         name: toolName,
-        description: `[${manifest.name}] ${toolDef.description}`,
+        description: buildToolDescription(manifest, toolDef),
         schema: z.object(schemaShape),
       }
     )
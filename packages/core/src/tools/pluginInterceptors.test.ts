@@ -0,0 +1,119 @@
+/**
+ * 插件工具调用拦截器测试
+ */
+
+import { describe, it, expect, vi } from 'vitest'
+import {
+  runWithInterceptors,
+  rejectToolCall,
+  createAuditInterceptor,
+  createRateLimitInterceptor,
+} from './pluginInterceptors'
+import type { PluginToolInterceptor } from './pluginInterceptors'
+import { createPluginTools } from './plugin.tools'
+
+const target = { pluginId: 'bridge', toolName: 'list_directory' }
+const ok = async (args: Record<string, unknown>) => ({ success: true, data: args })
+
+describe('runWithInterceptors', () => {
+  it('should let before rewrite args and after rewrite results', async () => {
+    const interceptor: PluginToolInterceptor = {
+      name: 'rewrite',
+      before: (_context, args) => ({ ...args, path: '/safe' }),
+      after: (_context, _args, result) => ({ ...result, data: { wrapped: result.data } }),
+    }
+    const result = await runWithInterceptors([interceptor], target, { path: '/etc' }, ok)
+    expect(result.data).toEqual({ wrapped: { path: '/safe' } })
+  })
+
+  it('should stop on rejection without executing', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const execute = vi.fn(ok)
+    const deny: PluginToolInterceptor = { name: 'deny', before: () => rejectToolCall('未授权') }
+    const result = await runWithInterceptors([deny], target, {}, execute)
+
+    expect(execute).not.toHaveBeenCalled()
+    expect(result).toEqual({ success: false, data: null, error: '调用被拦截器 deny 拒绝: 未授权' })
+    warn.mockRestore()
+  })
+
+  it('should treat a throwing before as a rejection', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const execute = vi.fn(ok)
+    const broken: PluginToolInterceptor = {
+      name: 'broken',
+      before: () => {
+        throw new Error('boom')
+      },
+    }
+    const result = await runWithInterceptors([broken], target, {}, execute)
+
+    expect(execute).not.toHaveBeenCalled()
+    expect(result.success).toBe(false)
+    warn.mockRestore()
+  })
+
+  it('should run before in order and after in reverse order', async () => {
+    const order: string[] = []
+    const make = (name: string): PluginToolInterceptor => ({
+      name,
+      before: (_context, args) => {
+        order.push(`before:${name}`)
+        return args
+      },
+      after: (_context, _args, result) => {
+        order.push(`after:${name}`)
+        return result
+      },
+    })
+    await runWithInterceptors([make('a'), make('b')], target, {}, ok)
+    expect(order).toEqual(['before:a', 'before:b', 'after:b', 'after:a'])
+  })
+
+  it('should skip interceptors for other tools', async () => {
+    const other: PluginToolInterceptor = {
+      name: 'other',
+      tools: ['read_file'],
+      before: () => rejectToolCall('不应触发'),
+    }
+    const result = await runWithInterceptors([other], target, {}, ok)
+    expect(result.success).toBe(true)
+  })
+})
+
+describe('built-in interceptors', () => {
+  it('should audit each call', async () => {
+    const log = vi.fn()
+    await runWithInterceptors([createAuditInterceptor(log)], target, { path: '/tmp' }, ok)
+    expect(log).toHaveBeenCalledWith(
+      expect.objectContaining({ pluginId: 'bridge', toolName: 'list_directory', success: true })
+    )
+  })
+
+  it('should reject calls beyond the rate limit', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const limit = createRateLimitInterceptor(2, 60_000)
+    const results = []
+    for (let i = 0; i < 3; i++) {
+      results.push(await runWithInterceptors([limit], target, {}, ok))
+    }
+    expect(results.map((result) => result.success)).toEqual([true, true, false])
+    warn.mockRestore()
+  })
+})
+
+describe('createPluginTools interceptors', () => {
+  it('should wrap plugin tool execution', async () => {
+    const warn = vi.spyOn(console, 'warn').mockImplementation(() => {})
+    const executor = vi.fn(async () => ({ success: true, data: null }))
+    const manifest = { id: 'bridge', name: 'Bridge', tools: [{ name: 'list_directory', description: '列目录' }] }
+    const [listDirectory] = createPluginTools(manifest, executor, {
+      interceptors: [{ name: 'deny', before: () => rejectToolCall('只读模式') }],
+    })
+    const output = JSON.parse(await listDirectory.invoke({}))
+
+    expect(executor).not.toHaveBeenCalled()
+    expect(output.error).toBe('调用被拦截器 deny 拒绝: 只读模式')
+    warn.mockRestore()
+  })
+})
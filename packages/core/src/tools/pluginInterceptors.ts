@@ -0,0 +1,155 @@
+/**
+ * @Description 插件工具调用拦截器
+ * 在任何插件工具执行前后被调用，用于权限检查、审计、限流与结果改写
+ */
+import type { PluginProcessedResult } from './pluginResultProcessors'
+
+export interface PluginToolCallContext {
+  pluginId: string
+  toolName: string
+  /** 本次调用进入拦截器链的时间戳（毫秒） */
+  startedAt: number
+}
+
+/** before 拦截器返回它表示拒绝本次调用 */
+export class PluginToolRejection {
+  constructor(public readonly reason: string) {}
+}
+
+/**
+ * 在 before 拦截器中拒绝调用
+ */
+export function rejectToolCall(reason: string): PluginToolRejection {
+  return new PluginToolRejection(reason)
+}
+
+export interface PluginToolInterceptor {
+  name: string
+  /** 匹配的插件 id，缺省匹配全部插件 */
+  plugins?: string[]
+  /** 匹配的工具名（不含插件前缀），缺省匹配全部工具 */
+  tools?: string[]
+  /** 执行前调用，可返回改写后的参数，或返回 rejectToolCall(...) 拒绝执行 */
+  before?: (
+    context: PluginToolCallContext,
+    args: Record<string, unknown>
+  ) =>
+    | Record<string, unknown>
+    | PluginToolRejection
+    | Promise<Record<string, unknown> | PluginToolRejection>
+  /** 执行后调用，可改写结果 */
+  after?: (
+    context: PluginToolCallContext,
+    args: Record<string, unknown>,
+    result: PluginProcessedResult
+  ) => PluginProcessedResult | Promise<PluginProcessedResult>
+}
+
+/**
+ * 判断拦截器是否作用于该次调用
+ */
+function matchesInterceptor(
+  interceptor: PluginToolInterceptor,
+  context: { pluginId: string; toolName: string }
+): boolean {
+  if (interceptor.plugins && !interceptor.plugins.includes(context.pluginId)) return false
+  if (interceptor.tools && !interceptor.tools.includes(context.toolName)) return false
+  return true
+}
+
+/**
+ * 用拦截器包裹一次工具执行
+ * before 按注册顺序执行，抛错视为拒绝（权限检查宜失败即拒绝）；after 按相反顺序执行，抛错时跳过并保留结果
+ */
+export async function runWithInterceptors(
+  interceptors: PluginToolInterceptor[],
+  target: { pluginId: string; toolName: string },
+  args: Record<string, unknown>,
+  execute: (args: Record<string, unknown>) => Promise<PluginProcessedResult>
+): Promise<PluginProcessedResult> {
+  const context: PluginToolCallContext = { ...target, startedAt: Date.now() }
+  const matched = interceptors.filter((interceptor) => matchesInterceptor(interceptor, context))
+
+  let currentArgs = args
+  for (const interceptor of matched) {
+    if (!interceptor.before) continue
+    let decision: Record<string, unknown> | PluginToolRejection
+    try {
+      decision = await interceptor.before(context, currentArgs)
+    } catch (error) {
+      decision = rejectToolCall(error instanceof Error ? error.message : String(error))
+    }
+    if (decision instanceof PluginToolRejection) {
+      console.warn(
+        `[Plugins] ${context.pluginId}__${context.toolName} 被拦截器 ${interceptor.name} 拒绝:`,
+        decision.reason
+      )
+      return {
+        success: false,
+        data: null,
+        error: `调用被拦截器 ${interceptor.name} 拒绝: ${decision.reason}`,
+      }
+    }
+    currentArgs = decision
+  }
+
+  let result = await execute(currentArgs)
+  for (const interceptor of [...matched].reverse()) {
+    if (!interceptor.after) continue
+    try {
+      result = await interceptor.after(context, currentArgs, result)
+    } catch (error) {
+      console.warn(`[Plugins] 拦截器 ${interceptor.name} 的 after 执行失败，已跳过:`, error)
+    }
+  }
+  return result
+}
+
+/**
+ * 创建审计拦截器：每次调用结束后记录插件、工具、参数、是否成功与耗时
+ */
+export function createAuditInterceptor(
+  log: (entry: {
+    pluginId: string
+    toolName: string
+    args: Record<string, unknown>
+    success: boolean
+    durationMs: number
+  }) => void = (entry) => console.log('[Plugins] 审计:', entry)
+): PluginToolInterceptor {
+  return {
+    name: 'audit',
+    after: (context, args, result) => {
+      log({
+        pluginId: context.pluginId,
+        toolName: context.toolName,
+        args,
+        success: result.success,
+        durationMs: Date.now() - context.startedAt,
+      })
+      return result
+    },
+  }
+}
+
+/**
+ * 创建限流拦截器：每个插件工具在 windowMs 内最多执行 maxCalls 次，超出时拒绝
+ */
+export function createRateLimitInterceptor(maxCalls: number, windowMs: number): PluginToolInterceptor {
+  const calls = new Map<string, number[]>()
+  return {
+    name: 'rate-limit',
+    before: (context, args) => {
+      const key = `${context.pluginId}__${context.toolName}`
+      const now = Date.now()
+      const recent = (calls.get(key) ?? []).filter((time) => now - time < windowMs)
+      if (recent.length >= maxCalls) {
+        calls.set(key, recent)
+        return rejectToolCall(`${windowMs}ms 内最多调用 ${maxCalls} 次`)
+      }
+      recent.push(now)
+      calls.set(key, recent)
+      return args
+    },
+  }
+}
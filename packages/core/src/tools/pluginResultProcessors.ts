@@ -0,0 +1,128 @@
+/**
+ * @Description 插件工具结果后处理
+ * 在结果返回给 LLM 前按插件/工具匹配依次应用处理器（脱敏、截断等）
+ */
+import type { PluginToolResult } from './plugin.tools'
+
+/** 经过后处理的结果，可带处理器附加的标记 */
+export interface PluginProcessedResult extends PluginToolResult {
+  /** 结果是否被后处理截断 */
+  truncated?: boolean
+}
+
+export interface PluginResultContext {
+  pluginId: string
+  toolName: string
+}
+
+export interface PluginResultProcessor {
+  name: string
+  /** 匹配的插件 id，缺省匹配全部插件 */
+  plugins?: string[]
+  /** 匹配的工具名（不含插件前缀），缺省匹配全部工具 */
+  tools?: string[]
+  process: (
+    result: PluginProcessedResult,
+    context: PluginResultContext
+  ) => PluginProcessedResult | Promise<PluginProcessedResult>
+}
+
+/**
+ * 判断处理器是否作用于该次调用
+ */
+function matchesProcessor(processor: PluginResultProcessor, context: PluginResultContext): boolean {
+  if (processor.plugins && !processor.plugins.includes(context.pluginId)) return false
+  if (processor.tools && !processor.tools.includes(context.toolName)) return false
+  return true
+}
+
+/**
+ * 按顺序应用匹配的处理器，单个处理器出错时跳过它并保留上一步结果
+ */
+export async function applyResultProcessors(
+  processors: PluginResultProcessor[],
+  result: PluginToolResult,
+  context: PluginResultContext
+): Promise<PluginProcessedResult> {
+  let current: PluginProcessedResult = result
+  for (const processor of processors) {
+    if (!matchesProcessor(processor, context)) continue
+    try {
+      current = await processor.process(current, context)
+    } catch (error) {
+      console.warn(`[Plugins] 结果处理器 ${processor.name} 执行失败，已跳过:`, error)
+    }
+  }
+  return current
+}
+
+/** 内置的常见密钥模式，只匹配带固定前缀的令牌格式 */
+export const DEFAULT_REDACT_PATTERNS: RegExp[] = [
+  /sk-[A-Za-z0-9_-]{16,}/g,
+  /AKIA[0-9A-Z]{16}/g,
+  /gh[pousr]_[A-Za-z0-9]{36,}/g,
+  /Bearer\s+[A-Za-z0-9._~+/-]{16,}=*/g,
+]
+
+/**
+ * 长十六进制串模式，需通过 extraPatterns 显式启用
+ * 会同时命中文件哈希、git SHA、无连字符 UUID 等正常数据，不宜默认开启
+ */
+export const HEX_SECRET_PATTERN = /\b[0-9a-fA-F]{32,}\b/g
+
+const REDACTED = '[REDACTED]'
+
+/**
+ * 递归替换值中所有字符串里匹配的敏感信息
+ */
+function redactValue(value: unknown, patterns: RegExp[]): unknown {
+  if (typeof value === 'string') {
+    return patterns.reduce((text, pattern) => text.replace(pattern, REDACTED), value)
+  }
+  if (Array.isArray(value)) {
+    return value.map((item) => redactValue(item, patterns))
+  }
+  if (value && typeof value === 'object') {
+    return Object.fromEntries(
+      Object.entries(value).map(([key, item]) => [key, redactValue(item, patterns)])
+    )
+  }
+  return value
+}
+
+/**
+ * 创建敏感信息脱敏处理器，extraPatterns 追加在内置模式之后
+ */
+export function createRedactProcessor(extraPatterns: RegExp[] = []): PluginResultProcessor {
+  // String.replace 需要全局标志才能替换所有匹配
+  const patterns = [...DEFAULT_REDACT_PATTERNS, ...extraPatterns].map((pattern) =>
+    pattern.global ? pattern : new RegExp(pattern.source, `${pattern.flags}g`)
+  )
+
+  return {
+    name: 'redact',
+    process: (result) => ({
+      ...result,
+      data: redactValue(result.data, patterns),
+      error: result.error === undefined ? undefined : (redactValue(result.error, patterns) as string),
+    }),
+  }
+}
+
+/**
+ * 创建大小截断处理器，data 序列化后超过 maxChars 时截断并标注 truncated
+ */
+export function createTruncateProcessor(maxChars: number): PluginResultProcessor {
+  return {
+    name: 'truncate',
+    process: (result) => {
+      const text = typeof result.data === 'string' ? result.data : JSON.stringify(result.data)
+      if (text === undefined || text.length <= maxChars) return result
+      return {
+        ...result,
+        data: `${text.slice(0, maxChars)}\n...（结果过长，已截断，原长度 ${text.length} 字符）`,
+        truncated: true,
+      }
+    },
+  }
+}
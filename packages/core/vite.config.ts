@@ -8,6 +8,7 @@ export default defineConfig({
     vue(),
     dts({
       include: ['src/**/*'],
+      exclude: ['src/**/*.test.ts'],
       outDir: 'dist',
     }),
   ],